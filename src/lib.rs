@@ -21,7 +21,7 @@
 //! Try calls without spending spacetime on converting errors.
 //!
 //! Here goes a simple example.
-//! ```rust
+//! ```rust,ignore
 //! use std::fs::File;
 //! use std::rc::Rc;
 //! use sprite::Sprite;
@@ -59,7 +59,7 @@
 //! # Generated Code
 //! To provide simplest illustration of the derive macros work let us discuss
 //! the following peace of code.
-//! ```rust
+//! ```rust,ignore
 //! #[derive(SumError)]
 //! enum A {
 //!     A(std::io::Error)
@@ -68,16 +68,18 @@
 //! In order to check in the compile time that contained types implemet
 //! [Error](https://doc.rust-lang.org/std/error/trait.Error.html) trait we
 //! generate this code.
-//! ```rust
-//! struct ___AssertNameA
+//! ```rust,ignore
+//! #[allow(non_snake_case)]
+//! fn ___AssertNameA()
 //! where
-//!    std::io::Error: std::error::Error;
+//!    std::io::Error: std::error::Error + 'static,
+//! {}
 //! ```
 //! After that given types auto implements
 //! [Debug](https://doc.rust-lang.org/std/fmt/trait.Debug.html) and
 //! [Display](https://doc.rust-lang.org/std/fmt/trait.Display.html)
 //! traits.
-//! ```rust
+//! ```rust,ignore
 //! impl std::fmt::Debug for A {
 //!     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
 //!         match self {
@@ -97,7 +99,7 @@
 //! The following generated code is an example of default
 //! [Error](https://doc.rust-lang.org/std/error/trait.Error.html) trait
 //! implementation.
-//! ```rust
+//! ```rust,ignore
 //! impl std::error::Error for A {
 //!     fn description(&self) -> &str {
 //!         match self {
@@ -114,16 +116,90 @@
 //!             A::A(error) => Some(error),
 //!         }
 //!     }
+//!     fn provide<'a>(&'a self, request: &mut std::error::Request<'a>) {
+//!         match self {
+//!             A::A(error) => error.provide(request),
+//!         }
+//!     }
 //! }
 //! ```
+//! `provide` forwards whatever the contained error exposes (backtraces and
+//! other typed context) through the generated `Error` impl. It relies on
+//! `std::error::Request`, which is only available on a nightly toolchain
+//! with `#![feature(error_generic_member_access)]` enabled, so the method
+//! is only emitted when this crate's own `provide` Cargo feature is
+//! enabled; without it, `Error` is generated without a `provide` override
+//! and compiles on stable like the rest of the derive.
 //! The last generated auto trait is [From](https://doc.rust-lang.org/std/convert/trait.From.html).
-//! ```rust
+//! ```rust,ignore
 //! impl From<std::io::Error> for A {
 //!     fn from(error: std::io::Error) -> Self {
 //!         A::A(error)
 //!     }
 //! }
 //! ```
+//! If two variants wrap the same error type, the `From` impls above would
+//! conflict. Mark all but one of them with `#[sum_error(no_from)]` to
+//! suppress `From` generation for that variant; the macro still reports a
+//! spanned error at each of the two variants if the attribute is missing.
+//!
+//! # Generic enums
+//! The enum's type parameters, lifetimes, and where-clause are threaded
+//! through every generated impl, so an enum like
+//! `enum CombineError<T: Debug + 'static> { A(GenErr<T>) }` derives correctly.
+//! Contained error types must still be `'static`, though: `source`,
+//! `cause`, and `provide` all coerce the contained error to
+//! `dyn Error + 'static`, so a variant holding a borrowed (non-`'static`)
+//! error, or a type parameter without a `'static` bound, fails to compile
+//! inside the generated `Error` impl rather than deriving successfully.
+//! The assertion generated for each variant does not catch this case: its
+//! bounds on types that involve the enum's generic parameters are assumed
+//! by the assertion function, not checked.
+//!
+//! # Custom Display messages
+//! By default the generated `Display` impl simply forwards the contained
+//! error's own message. Annotate a variant with `#[error("...")]` to
+//! override it with a custom format string; the placeholder `{0}` is
+//! substituted with the contained error, so it can be interpolated into
+//! the message (format specs such as `{0:?}` are preserved).
+//! ```rust,ignore
+//! #[derive(SumError)]
+//! enum A {
+//!     #[error("failed to load sprite: {0}")]
+//!     A(std::io::Error),
+//! }
+//! ```
+//!
+//! # Localized Display
+//! Adding `#[sum_error(localized)]` on the enum swaps the generated
+//! `Display` impl from forwarding the inner error's own message to looking
+//! up a stable per-variant key through a `Localize` trait that the
+//! application brings into scope and implements against its own message
+//! catalog. Because this crate's `proc-macro` crate type cannot itself
+//! export ordinary items alongside `#[proc_macro_derive]`, `Localize` is
+//! not defined here: declare it yourself with the signature below and
+//! implement it for the enum.
+//! ```rust,ignore
+//! trait Localize {
+//!     fn localize(key: &str, error: &dyn std::error::Error) -> String;
+//! }
+//!
+//! #[derive(SumError)]
+//! #[sum_error(localized)]
+//! enum A {
+//!     #[error(key = "io.read_failed")]
+//!     A(std::io::Error),
+//! }
+//!
+//! impl Localize for A {
+//!     fn localize(key: &str, error: &dyn std::error::Error) -> String {
+//!         format!("{}: {}", key, error)
+//!     }
+//! }
+//! ```
+//! A variant without an explicit `key` falls back to its lowercased ident.
+//! The macro also emits `A::VARIANT_KEYS`, listing every variant's key, so
+//! callers can check their catalog covers them all.
 //!
 
 extern crate proc_macro;
@@ -131,133 +207,279 @@ extern crate proc_macro;
 use proc_macro::TokenStream as TS1;
 use proc_macro2::TokenStream as TS2;
 use syn::Data::Enum;
-use syn::{DeriveInput, Fields, parse_macro_input};
+use syn::{DeriveInput, Fields, LitStr, parse_macro_input};
 use syn::spanned::Spanned;
 use quote::{quote_spanned, quote, format_ident};
 
 /// The whole point. Refer to the whole crate desription for a guide.
-#[proc_macro_derive(SumError)]
+#[proc_macro_derive(SumError, attributes(error, sum_error))]
 pub fn derive_sum_error(stream: TS1) -> TS1 {
     let input = parse_macro_input!(stream as DeriveInput);
     let name = input.ident;
-    match input.data {
-        Enum(enum_body) => enum_body
-            .variants
-            .iter()
-            .fold(Ok(StreamHolder::new()), |holder_r, var| { holder_r.and_then(|mut holder| {
-                match &var.fields {
-                    Fields::Unnamed(fields) =>
-                        Ok(&fields.unnamed)
-                            .check(|u| { u.len() == 1 }, |_| {
-                                "Invalid number of contained errors! Only one error allowed in any enum variant!"
-                            }).and_then(|u| {
-                                let var_name = &var.ident;
+    let generics = input.generics;
+    let attrs = input.attrs;
+    let localized = has_sum_error_flag(&attrs, "localized");
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let enum_body = match input.data {
+        Enum(enum_body) => enum_body,
+        _ => {
+            let err = syn::Error::new_spanned(
+                &name,
+                "Deriving from SumError is only avaliable for enums of errors!",
+            ).to_compile_error();
+            return TS1::from(err);
+        }
+    };
+
+    let (it, errors) = enum_body.variants.iter().fold(
+        (StreamHolder::new(), Vec::<TS2>::new()),
+        |(mut holder, mut errors), var| {
+            match &var.fields {
+                Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                    let u = &fields.unnamed;
+                    let var_name = &var.ident;
+
+                    for attr in var.attrs.iter().filter(|attr| attr.path.is_ident("error")) {
+                        let is_message = attr.parse_args::<LitStr>().is_ok();
+                        let is_key = attr
+                            .parse_args::<syn::MetaNameValue>()
+                            .ok()
+                            .is_some_and(|nv| {
+                                nv.path.is_ident("key") && matches!(nv.lit, syn::Lit::Str(_))
+                            });
+                        if !is_message && !is_key {
+                            errors.push(
+                                syn::Error::new_spanned(
+                                    attr,
+                                    "`#[error(...)]` must be either a string literal message or `key = \"...\"`",
+                                )
+                                .to_compile_error(),
+                            );
+                        } else if is_key && !localized {
+                            errors.push(
+                                syn::Error::new_spanned(
+                                    attr,
+                                    "`#[error(key = \"...\")]` has no effect without `#[sum_error(localized)]` on the enum",
+                                )
+                                .to_compile_error(),
+                            );
+                        } else if is_message && localized {
+                            errors.push(
+                                syn::Error::new_spanned(
+                                    attr,
+                                    "plain `#[error(\"...\")]` messages are ignored under `#[sum_error(localized)]`; use `#[error(key = \"...\")]` instead",
+                                )
+                                .to_compile_error(),
+                            );
+                        }
+                    }
 
-                                let assert_error = {
-                                    let ty = &u[0].ty;
-                                    let ty_span = ty.span();
-                                    quote_spanned!(ty_span=> #ty: std::error::Error,)
-                                };
-                                holder.check_streams.push(assert_error);
+                    let assert_error = {
+                        let ty = &u[0].ty;
+                        let ty_span = ty.span();
+                        // `source`/`cause`/`provide` below coerce the contained
+                        // error to `dyn Error + 'static`, so the assertion lists
+                        // `'static` too. Only bounds on concrete types are
+                        // checked here; for a type involving the enum's generic
+                        // parameters the bound is assumed, and a non-'static
+                        // error type fails inside the generated `Error` impl.
+                        quote_spanned!(ty_span=> #ty: std::error::Error + 'static,)
+                    };
+                    holder.check_streams.push(assert_error);
 
-                                let match_stream = {
-                                    let name_local = name.clone();
-                                    let var_name_local = var_name.clone();
-                                    Box::new(move |process| {
-                                        quote!(#name_local::#var_name_local(error) => #process,)
-                                    })
-                                };
-                                holder.match_streams.push(match_stream);
+                    let match_stream = {
+                        let name_local = name.clone();
+                        let var_name_local = var_name.clone();
+                        Box::new(move |process| {
+                            quote!(#name_local::#var_name_local(error) => #process,)
+                        })
+                    };
+                    holder.match_streams.push(match_stream);
 
-                                let into_stream = {
-                                    let name_local = name.clone();
-                                    let var_name_local = var_name.clone();
-                                    let ty = &u[0].ty;
-                                    let ty_span = ty.span();
-                                    quote_spanned! {ty_span=>
-                                        impl From<#ty> for #name_local {
-                                            fn from(error: #ty) -> Self {
-                                                #name_local::#var_name_local(error)
-                                            }
+                    let display_stream = if localized {
+                        let name_local = name.clone();
+                        let var_name_local = var_name.clone();
+                        let key = variant_key(var);
+                        holder.variant_keys.push(key.clone());
+                        quote! {
+                            #name_local::#var_name_local(error) =>
+                                write!(f, "{}", <#name_local #ty_generics as Localize>::localize(#key, error)),
+                        }
+                    } else {
+                        let name_local = name.clone();
+                        let var_name_local = var_name.clone();
+                        let message = custom_display_message(var).map(|lit| {
+                            LitStr::new(&substitute_positional_arg(&lit.value()), lit.span())
+                        });
+                        match message {
+                            Some(lit) => quote!(#name_local::#var_name_local(error) => write!(f, #lit),),
+                            None => quote!(#name_local::#var_name_local(error) => write!(f, "{}", error),),
+                        }
+                    };
+                    holder.display_streams.push(display_stream);
+
+                    if has_sum_error_flag(&var.attrs, "no_from") {
+                        // `From` generation suppressed for this variant.
+                    } else {
+                        let ty = &u[0].ty;
+                        let ty_key = quote!(#ty).to_string();
+                        if let Some(seen_var) = holder.seen_from_types.get(&ty_key) {
+                            errors.push(syn::Error::new_spanned(
+                                seen_var,
+                                format!(
+                                    "Duplicate `From` impl: variant `{}` already wraps this error type; add `#[sum_error(no_from)]` to one of them",
+                                    seen_var,
+                                ),
+                            ).to_compile_error());
+                            errors.push(syn::Error::new_spanned(
+                                var_name,
+                                format!(
+                                    "Duplicate `From` impl: variant `{}` already wraps this error type; add `#[sum_error(no_from)]` to one of them",
+                                    seen_var,
+                                ),
+                            ).to_compile_error());
+                        } else {
+                            holder.seen_from_types.insert(ty_key, var_name.clone());
+
+                            let into_stream = {
+                                let name_local = name.clone();
+                                let var_name_local = var_name.clone();
+                                let ty_span = ty.span();
+                                quote_spanned! {ty_span=>
+                                    impl #impl_generics From<#ty> for #name_local #ty_generics #where_clause {
+                                        fn from(error: #ty) -> Self {
+                                            #name_local::#var_name_local(error)
                                         }
                                     }
-                                };
-                                holder.into_streams.push(into_stream);
+                                }
+                            };
+                            holder.into_streams.push(into_stream);
+                        }
+                    }
+                }
+                Fields::Unnamed(fields) => {
+                    errors.push(syn::Error::new_spanned(
+                        &fields.unnamed,
+                        "Invalid number of contained errors! Only one error allowed in any enum variant!",
+                    ).to_compile_error());
+                }
+                _ => {
+                    errors.push(syn::Error::new_spanned(
+                        &var.ident,
+                        "Contained in variants errors should be unnamed!",
+                    ).to_compile_error());
+                }
+            }
+            (holder, errors)
+        },
+    );
 
-                                Ok(holder)
-                            }),
-                    _ => Err("Contained in variants errors should be unnamed!")
+    if !errors.is_empty() {
+        let mut result = TS2::new();
+        result.extend(errors);
+        return TS1::from(result);
+    }
+
+    let mut result = TS2::new();
+
+    {
+        let mut local = TS2::new();
+        if let Some(where_clause) = where_clause {
+            let predicates = &where_clause.predicates;
+            local.extend(quote!{ #predicates, });
+        }
+        local.extend(it.check_streams);
+        let assert_name = format_ident!("___{}{}", "AssertName", name);
+        result.extend(vec![quote!{
+            #[allow(non_snake_case)]
+            fn #assert_name #impl_generics () where #local {}
+        }])
+    }
+
+    let local_debug = create_stream(&it.match_streams, quote!{ write!(f, "{:?}", error) });
+    let local_display = {
+        let mut local = TS2::new();
+        local.extend(it.display_streams);
+        local
+    };
+    let local_description = create_stream(&it.match_streams, quote!{ error.description() });
+    let local_cause = create_stream(&it.match_streams, quote!{ Some(error) });
+    let local_source = create_stream(&it.match_streams, quote!{ Some(error) });
+    let provide_method = if cfg!(feature = "provide") {
+        let local_provide = create_stream(&it.match_streams, quote!{ error.provide(request) });
+        Some(quote! {
+            fn provide<'a>(&'a self, request: &mut std::error::Request<'a>) {
+                match self {
+                    #local_provide
                 }
-            })}).map(|it| {
-                let mut result = TS2::new();
+            }
+        })
+    } else {
+        None
+    };
 
-                {
-                    let mut local = TS2::new();
-                    local.extend(it.check_streams);
-                    let assert_name = format_ident!("___{}{}", "AssertName", name);
-                    result.extend(vec![quote!{
-                        struct #assert_name where #local;
-                    }])
+    result.extend(vec![quote! {
+        impl #impl_generics std::fmt::Debug for #name #ty_generics #where_clause {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                match self {
+                    #local_debug
                 }
+            }
+        }
 
-                let local_debug = create_stream(&it.match_streams, quote!{ write!(f, "{:?}", error) });
-                let local_display = create_stream(&it.match_streams, quote!{ write!(f, "{}", error) });
-                let local_description = create_stream(&it.match_streams, quote!{ error.description() });
-                let local_cause = create_stream(&it.match_streams, quote!{ Some(error) });
-                let local_source = create_stream(&it.match_streams, quote!{ Some(error) });
+        impl #impl_generics std::fmt::Display for #name #ty_generics #where_clause {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                match self {
+                    #local_display
+                }
+            }
+        }
 
-                result.extend(vec![quote! {
-                    impl std::fmt::Debug for #name {
-                        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-                            match self {
-                                #local_debug
-                            }
-                        }
-                    }
+        impl #impl_generics std::error::Error for #name #ty_generics #where_clause {
+            fn description(&self) -> &str {
+                match self {
+                    #local_description
+                }
+            }
 
-                    impl std::fmt::Display for #name {
-                        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-                            match self {
-                                #local_display
-                            }
-                        }
-                    }
+            fn cause(&self) -> Option<&dyn std::error::Error> {
+                match self {
+                    #local_cause
+                }
+            }
 
-                    impl std::error::Error for #name {
-                        fn description(&self) -> &str {
-                            match self {
-                                #local_description
-                            }
-                        }
+            fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+                match self {
+                    #local_source
+                }
+            }
 
-                        fn cause(&self) -> Option<&dyn std::error::Error> {
-                            match self {
-                                #local_cause
-                            }
-                        }
+            #provide_method
+        }
+    }]);
 
-                        fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
-                            match self {
-                                #local_source
-                            }
-                        }
-                    }
-                }]);
+    result.extend(it.into_streams);
 
-                result.extend(it.into_streams);
+    if localized {
+        let keys = it.variant_keys;
+        result.extend(vec![quote! {
+            impl #impl_generics #name #ty_generics #where_clause {
+                pub const VARIANT_KEYS: &'static [&'static str] = &[#(#keys),*];
+            }
+        }]);
+    }
 
-                result
-            }),
-        _ => Err("Deriving from SumError is only avaliable for enums of errors!")
-    }.map(|it| TS1::from(it))
-    .unwrap()
+    TS1::from(result)
 }
 
 type TemplateStreams = Vec<Box<dyn Fn(TS2) -> TS2>>;
 struct StreamHolder {
     check_streams: Vec<TS2>,
     match_streams: TemplateStreams,
+    display_streams: Vec<TS2>,
     into_streams: Vec<TS2>,
+    seen_from_types: std::collections::HashMap<String, syn::Ident>,
+    variant_keys: Vec<String>,
 }
 
 impl StreamHolder {
@@ -265,26 +487,101 @@ impl StreamHolder {
         StreamHolder {
             check_streams: Vec::new(),
             match_streams: Vec::new(),
+            display_streams: Vec::new(),
             into_streams: Vec::new(),
+            seen_from_types: std::collections::HashMap::new(),
+            variant_keys: Vec::new(),
         }
     }
 }
 
-fn create_stream(streams: &TemplateStreams, t: TS2) -> TS2 {
-    let mut local = TS2::new();
-    local.extend(streams.iter().map(|mat| {mat(t.clone())}));
-    local
+/// Resolves the localized message key for a variant: an explicit
+/// `#[error(key = "...")]` override, or the variant's ident lowercased.
+fn variant_key(var: &syn::Variant) -> String {
+    var.attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("error"))
+        .find_map(|attr| {
+            attr.parse_args::<syn::MetaNameValue>().ok().and_then(|nv| {
+                if !nv.path.is_ident("key") {
+                    return None;
+                }
+                match nv.lit {
+                    syn::Lit::Str(s) => Some(s.value()),
+                    _ => None,
+                }
+            })
+        })
+        .unwrap_or_else(|| var.ident.to_string().to_lowercase())
 }
 
-trait ResultExtender<V, E, R> where Self: std::marker::Sized {
-    fn check<C: FnOnce(&V) -> bool, T: FnOnce(V) -> E>(self, _: C, _: T) -> Result<R, E>;
+/// Checks whether an attribute list (of the enum or of a variant) carries
+/// `#[sum_error(flag)]`.
+fn has_sum_error_flag(attrs: &[syn::Attribute], flag: &str) -> bool {
+    attrs.iter().any(|attr| {
+        attr.path.is_ident("sum_error")
+            && attr
+                .parse_args::<syn::Ident>()
+                .map(|ident| ident == flag)
+                .unwrap_or(false)
+    })
 }
 
-impl<V, E> ResultExtender<V, E, V> for Result<V, E> {
-    fn check<C: FnOnce(&V) -> bool, T: FnOnce(V) -> E>(self, check_block: C, throw_block: T) -> Result<V, E> {
-        self.and_then(|v| {
-            if check_block(&v) { Ok(v) } else { Err(throw_block(v)) }
-        })
+/// Reads an optional `#[error("...")]` attribute off a variant, returning the
+/// format string literal it carries, if any.
+fn custom_display_message(var: &syn::Variant) -> Option<LitStr> {
+    var.attrs
+        .iter()
+        .find(|attr| attr.path.is_ident("error"))
+        .and_then(|attr| attr.parse_args::<LitStr>().ok())
+}
+
+/// Rewrites references to positional argument `0` (e.g. `{0}`, `{0:?}`,
+/// `{0:#x}`) into named references to `error`, leaving escaped braces
+/// (`{{`, `}}`) and any other placeholder untouched.
+fn substitute_positional_arg(message: &str) -> String {
+    let mut out = String::with_capacity(message.len());
+    let bytes = message.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = message[i..].chars().next().unwrap();
+        if c == '{' && message[i + 1..].starts_with('{') {
+            out.push_str("{{");
+            i += 2;
+        } else if c == '{' {
+            match message[i + 1..].find('}') {
+                Some(rel_end) => {
+                    let end = i + 1 + rel_end;
+                    let spec = &message[i + 1..end];
+                    if spec == "0" {
+                        out.push_str("{error}");
+                    } else if let Some(rest) = spec.strip_prefix("0:") {
+                        out.push_str("{error:");
+                        out.push_str(rest);
+                        out.push('}');
+                    } else {
+                        out.push_str(&message[i..=end]);
+                    }
+                    i = end + 1;
+                }
+                None => {
+                    out.push(c);
+                    i += 1;
+                }
+            }
+        } else if c == '}' && message[i + 1..].starts_with('}') {
+            out.push_str("}}");
+            i += 2;
+        } else {
+            out.push(c);
+            i += c.len_utf8();
+        }
     }
+    out
+}
 
+fn create_stream(streams: &TemplateStreams, t: TS2) -> TS2 {
+    let mut local = TS2::new();
+    local.extend(streams.iter().map(|mat| {mat(t.clone())}));
+    local
 }